@@ -0,0 +1,156 @@
+//! Online backup, for copying between `DatabaseConnection`s while they
+//! stay open.
+//!
+//! cf. [sqlite3_backup_init][init] and friends.
+//!
+//! [init]: http://www.sqlite.org/c3ref/backup_finish.html
+
+use libc::{c_char, c_int};
+use std::ffi::CString;
+use std::marker::PhantomData;
+
+use super::core::DatabaseConnection;
+use super::{SqliteError, SqliteResult};
+use super::SqliteErrorCode::{SQLITE_BUSY, SQLITE_ERROR, SQLITE_LOCKED};
+
+#[allow(non_camel_case_types)]
+enum sqlite3_backup {}
+
+const SQLITE_DONE: c_int = 101;
+
+extern "C" {
+    fn sqlite3_backup_init(
+        dst: *mut super::core::RawConnection,
+        zDestName: *const c_char,
+        src: *mut super::core::RawConnection,
+        zSourceName: *const c_char,
+    ) -> *mut sqlite3_backup;
+
+    fn sqlite3_backup_step(backup: *mut sqlite3_backup, n_page: c_int) -> c_int;
+    fn sqlite3_backup_finish(backup: *mut sqlite3_backup) -> c_int;
+    fn sqlite3_backup_remaining(backup: *mut sqlite3_backup) -> c_int;
+    fn sqlite3_backup_pagecount(backup: *mut sqlite3_backup) -> c_int;
+}
+
+/// The outcome of a single `Backup::step()` call.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum StepResult {
+    /// The backup finished; there is nothing left to copy.
+    Done,
+    /// Progress was made; call `step()` again to continue.
+    More,
+    /// The destination database was busy; retry after a short sleep.
+    Busy,
+    /// A table in the source database was locked; retry after a short sleep.
+    Locked,
+}
+
+/// A backup of one database onto another, stepped incrementally so
+/// that a live, in-use database can be copied a few pages at a time.
+///
+/// Borrows `src` and `dst` for as long as the backup is in progress,
+/// so the borrow checker (rather than a dangling `sqlite3_backup*`)
+/// stops either connection from closing underneath it.
+pub struct Backup<'s, 'd> {
+    backup: *mut sqlite3_backup,
+    _src: PhantomData<&'s DatabaseConnection>,
+    _dst: PhantomData<&'d mut DatabaseConnection>,
+}
+
+impl<'s, 'd> Backup<'s, 'd> {
+    /// Start a backup copying `src_name` (typically `"main"`) of `src`
+    /// onto `dst_name` of `dst`.
+    pub fn new(src: &'s DatabaseConnection, src_name: &str, dst: &'d mut DatabaseConnection, dst_name: &str) -> SqliteResult<Backup<'s, 'd>> {
+        let c_src_name = CString::new(src_name).expect("src_name");
+        let c_dst_name = CString::new(dst_name).expect("dst_name");
+
+        let backup = unsafe {
+            sqlite3_backup_init(dst.raw(), c_dst_name.as_ptr(), src.raw(), c_src_name.as_ptr())
+        };
+        if backup.is_null() {
+            return Err(SqliteError {
+                kind: SQLITE_ERROR,
+                desc: "sqlite3_backup_init failed",
+                detail: None,
+            });
+        }
+        Ok(Backup { backup, _src: PhantomData, _dst: PhantomData })
+    }
+
+    /// Copy up to `n_pages` pages (or all remaining pages, if
+    /// negative) from the source to the destination.
+    pub fn step(&mut self, n_pages: i32) -> SqliteResult<StepResult> {
+        match unsafe { sqlite3_backup_step(self.backup, n_pages as c_int) } {
+            SQLITE_DONE => Ok(StepResult::Done),
+            rc if rc == SQLITE_BUSY as c_int => Ok(StepResult::Busy),
+            rc if rc == SQLITE_LOCKED as c_int => Ok(StepResult::Locked),
+            0 => Ok(StepResult::More),
+            rc => Err(SqliteError {
+                kind: SQLITE_ERROR,
+                desc: "sqlite3_backup_step failed",
+                detail: Some(format!("result code {}", rc)),
+            }),
+        }
+    }
+
+    /// `(pages_remaining, pages_total)` as of the last `step()` call.
+    pub fn progress(&self) -> (i32, i32) {
+        unsafe {
+            (sqlite3_backup_remaining(self.backup) as i32, sqlite3_backup_pagecount(self.backup) as i32)
+        }
+    }
+}
+
+impl<'s, 'd> Drop for Backup<'s, 'd> {
+    fn drop(&mut self) {
+        unsafe { sqlite3_backup_finish(self.backup); }
+    }
+}
+
+impl DatabaseConnection {
+    /// Copy all of `src_name` (typically `"main"`) onto `dst_name` of
+    /// `dst` in one go.
+    pub fn backup(&self, src_name: &str, dst: &mut DatabaseConnection, dst_name: &str) -> SqliteResult<()> {
+        let mut backup = Backup::new(self, src_name, dst, dst_name)?;
+        loop {
+            match backup.step(-1)? {
+                StepResult::Done => return Ok(()),
+                StepResult::More => continue,
+                StepResult::Busy | StepResult::Locked => {
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DatabaseConnection, Query, ResultRowAccess, SqliteResult, StatementUpdate};
+
+    #[test]
+    fn backup_copies_all_rows() {
+        fn go() -> SqliteResult<Vec<i32>> {
+            let mut src = DatabaseConnection::in_memory()?;
+            src.exec("CREATE TABLE t (n)")?;
+            {
+                let mut tx = src.prepare("INSERT INTO t (n) VALUES (?)")?;
+                for n in 1..4 {
+                    tx.update(&[&n])?;
+                }
+            }
+
+            let mut dst = DatabaseConnection::in_memory()?;
+            src.backup("main", &mut dst, "main")?;
+
+            let mut stmt = dst.prepare("SELECT n FROM t ORDER BY n")?;
+            let mut ns = vec!();
+            stmt.query(&[], &mut |row| {
+                ns.push(row.get(0));
+                Ok(())
+            })?;
+            Ok(ns)
+        }
+        assert_eq!(go().unwrap(), vec![1, 2, 3]);
+    }
+}
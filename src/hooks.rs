@@ -0,0 +1,184 @@
+//! Commit, rollback, and data-change hooks.
+//!
+//! cf. [sqlite3_commit_hook][commit], [sqlite3_rollback_hook][rollback],
+//! and [sqlite3_update_hook][update].
+//!
+//! [commit]: http://www.sqlite.org/c3ref/commit_hook.html
+//! [rollback]: http://www.sqlite.org/c3ref/commit_hook.html
+//! [update]: http://www.sqlite.org/c3ref/update_hook.html
+//!
+//! *Known limitation*: as with `trace`/`profile` in `mod trace`, a
+//! boxed hook closure is only freed when it is replaced by a later
+//! call that hands the previous argument back. Dropping a connection
+//! with a hook still installed, without uninstalling it first via
+//! `None`, leaks that box for the life of the process.
+//!
+//!   - *TODO: once `DatabaseConnection`'s `Drop` has a hook for
+//!     per-module teardown, call `commit_hook(None)`/`rollback_hook(None)`/
+//!     `update_hook(None)` from it so this is no longer permanent debt.*
+
+use libc::{c_char, c_int, c_void};
+use std::ffi::CStr;
+
+use super::core::DatabaseConnection;
+
+/// The kind of change reported to an `update_hook` callback.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Action {
+    /// A row was inserted.
+    Insert,
+    /// A row was updated.
+    Update,
+    /// A row was deleted.
+    Delete,
+}
+
+const SQLITE_INSERT: c_int = 18;
+const SQLITE_UPDATE: c_int = 23;
+const SQLITE_DELETE: c_int = 9;
+
+extern "C" {
+    fn sqlite3_commit_hook(
+        db: *mut super::core::RawConnection,
+        xCallback: Option<unsafe extern "C" fn(*mut c_void) -> c_int>,
+        pArg: *mut c_void,
+    ) -> *mut c_void;
+
+    fn sqlite3_rollback_hook(
+        db: *mut super::core::RawConnection,
+        xCallback: Option<unsafe extern "C" fn(*mut c_void)>,
+        pArg: *mut c_void,
+    ) -> *mut c_void;
+
+    fn sqlite3_update_hook(
+        db: *mut super::core::RawConnection,
+        xCallback: Option<unsafe extern "C" fn(*mut c_void, c_int, *const c_char, *const c_char, i64)>,
+        pArg: *mut c_void,
+    ) -> *mut c_void;
+}
+
+impl DatabaseConnection {
+    /// Install (or, with `None`, remove) a callback invoked just
+    /// before a transaction commits. Returning `true` aborts the
+    /// commit, turning it into a rollback.
+    pub fn commit_hook(&mut self, f: Option<Box<dyn FnMut() -> bool>>) {
+        unsafe {
+            let old = match f {
+                Some(cb) => {
+                    let boxed = Box::into_raw(Box::new(cb));
+                    sqlite3_commit_hook(self.raw(), Some(commit_trampoline), boxed as *mut c_void)
+                }
+                None => sqlite3_commit_hook(self.raw(), None, std::ptr::null_mut()),
+            };
+            if !old.is_null() {
+                drop(Box::from_raw(old as *mut Box<dyn FnMut() -> bool>));
+            }
+        }
+    }
+
+    /// Install (or, with `None`, remove) a callback invoked whenever a
+    /// transaction is rolled back.
+    pub fn rollback_hook(&mut self, f: Option<Box<dyn FnMut()>>) {
+        unsafe {
+            let old = match f {
+                Some(cb) => {
+                    let boxed = Box::into_raw(Box::new(cb));
+                    sqlite3_rollback_hook(self.raw(), Some(rollback_trampoline), boxed as *mut c_void)
+                }
+                None => sqlite3_rollback_hook(self.raw(), None, std::ptr::null_mut()),
+            };
+            if !old.is_null() {
+                drop(Box::from_raw(old as *mut Box<dyn FnMut()>));
+            }
+        }
+    }
+
+    /// Install (or, with `None`, remove) a callback invoked for every
+    /// row inserted, updated, or deleted outside of a `TRUNCATE`.
+    pub fn update_hook(&mut self, f: Option<Box<dyn FnMut(Action, &str, &str, i64)>>) {
+        unsafe {
+            let old = match f {
+                Some(cb) => {
+                    let boxed = Box::into_raw(Box::new(cb));
+                    sqlite3_update_hook(self.raw(), Some(update_trampoline), boxed as *mut c_void)
+                }
+                None => sqlite3_update_hook(self.raw(), None, std::ptr::null_mut()),
+            };
+            if !old.is_null() {
+                drop(Box::from_raw(old as *mut Box<dyn FnMut(Action, &str, &str, i64)>));
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn commit_trampoline(arg: *mut c_void) -> c_int {
+    let cb = &mut *(arg as *mut Box<dyn FnMut() -> bool>);
+    if cb() { 1 } else { 0 }
+}
+
+unsafe extern "C" fn rollback_trampoline(arg: *mut c_void) {
+    let cb = &mut *(arg as *mut Box<dyn FnMut()>);
+    cb();
+}
+
+unsafe extern "C" fn update_trampoline(arg: *mut c_void, action: c_int, db: *const c_char, table: *const c_char, rowid: i64) {
+    let cb = &mut *(arg as *mut Box<dyn FnMut(Action, &str, &str, i64)>);
+    let action = match action {
+        SQLITE_INSERT => Action::Insert,
+        SQLITE_UPDATE => Action::Update,
+        SQLITE_DELETE => Action::Delete,
+        _ => return,
+    };
+    if let (Ok(db), Ok(table)) = (CStr::from_ptr(db).to_str(), CStr::from_ptr(table).to_str()) {
+        cb(action, db, table, rowid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::super::{DatabaseConnection, SqliteResult};
+    use super::Action;
+
+    #[test]
+    fn commit_hook_can_veto_a_commit() {
+        fn go() -> SqliteResult<i64> {
+            let mut db = DatabaseConnection::in_memory()?;
+            db.exec("CREATE TABLE t (n)")?;
+            db.commit_hook(Some(Box::new(|| true)));
+            db.exec("INSERT INTO t (n) VALUES (1)").ok();
+            db.commit_hook(None);
+            db.exec("SELECT count(*) FROM t")?;
+            let mut stmt = db.prepare("SELECT count(*) FROM t")?;
+            let mut rows = stmt.execute();
+            let count = match rows.step()? {
+                Some(ref mut row) => row.column_int64(0),
+                None => panic!("expected a row"),
+            };
+            Ok(count)
+        }
+        assert_eq!(go().unwrap(), 0);
+    }
+
+    #[test]
+    fn update_hook_reports_inserted_rows() {
+        fn go() -> SqliteResult<Vec<(Action, String)>> {
+            let seen = Rc::new(RefCell::new(vec!()));
+            let mut db = DatabaseConnection::in_memory()?;
+            db.exec("CREATE TABLE t (n)")?;
+            {
+                let seen = seen.clone();
+                db.update_hook(Some(Box::new(move |action, _db, table, _rowid| {
+                    seen.borrow_mut().push((action, table.to_string()));
+                })));
+            }
+            db.exec("INSERT INTO t (n) VALUES (1)")?;
+            db.update_hook(None);
+            Ok(Rc::try_unwrap(seen).unwrap().into_inner())
+        }
+        let seen = go().unwrap();
+        assert_eq!(seen, vec![(Action::Insert, "t".to_string())]);
+    }
+}
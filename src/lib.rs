@@ -95,11 +95,20 @@ pub use core::Access;
 pub use core::{DatabaseConnection, PreparedStatement, ResultSet, ResultRow, Value, Context};
 pub use core::{ColIx, ParamIx};
 pub use types::{FromSql, ToSql};
+pub use function::Aggregate;
+pub use blob::BlobHandle;
+pub use backup::{Backup, StepResult};
+pub use hooks::Action;
 
 use self::SqliteErrorCode::SQLITE_MISUSE;
 
 pub mod core;
 pub mod types;
+pub mod function;
+pub mod blob;
+pub mod backup;
+pub mod trace;
+pub mod hooks;
 
 /// bindgen-bindings to libsqlite3
 #[allow(non_camel_case_types, non_snake_case)]
@@ -190,6 +199,148 @@ fn bind_values(s: &mut PreparedStatement, values: &[&dyn ToSql]) -> SqliteResult
     Ok(())
 }
 
+/// An iterator over rows produced by `query_map`, mapping each one
+/// through `F` as it is stepped.
+///
+/// Driving this with `Iterator::next` lazily calls `ResultSet::step`,
+/// so rows are only pulled from sqlite as the caller asks for them.
+pub struct MappedRows<'stmt, T, F>
+    where F: FnMut(&mut ResultRow) -> SqliteResult<T>
+{
+    results: core::ResultSet<'stmt>,
+    f: F,
+}
+
+impl<'stmt, T, F> Iterator for MappedRows<'stmt, T, F>
+    where F: FnMut(&mut ResultRow) -> SqliteResult<T>
+{
+    type Item = SqliteResult<T>;
+
+    fn next(&mut self) -> Option<SqliteResult<T>> {
+        match self.results.step() {
+            Ok(Some(ref mut row)) => Some((self.f)(row)),
+            Ok(None) => None,
+            Err(oops) => Some(Err(oops)),
+        }
+    }
+}
+
+/// Mix in a `query_map()` convenience function.
+pub trait QueryMap<T, F>
+    where F: FnMut(&mut ResultRow) -> SqliteResult<T>
+{
+    /// Bind parameters and return an iterator mapping each result row
+    /// through `f`.
+    fn query_map<'stmt>(&'stmt mut self,
+                        values: &[&dyn ToSql],
+                        f: F
+                        ) -> SqliteResult<MappedRows<'stmt, T, F>>;
+}
+
+impl<T, F> QueryMap<T, F> for core::PreparedStatement
+    where F: FnMut(&mut ResultRow) -> SqliteResult<T>
+{
+    /// Bind parameters and return an iterator mapping each result row
+    /// through `f`, e.g.
+    /// `stmt.query_map(&[], |r| Ok(r.get::<u32, i32>(0)))?.collect()`.
+    fn query_map<'stmt>(&'stmt mut self,
+                        values: &[&dyn ToSql],
+                        f: F
+                        ) -> SqliteResult<MappedRows<'stmt, T, F>>
+    {
+        bind_values(self, values)?;
+        let results = self.execute();
+        Ok(MappedRows { results, f })
+    }
+}
+
+impl core::PreparedStatement {
+    /// Look up the index of a named parameter (`:name`, `@name`, or
+    /// `$name`), for use with `bind_*` or the `*_named` methods below.
+    ///
+    /// cf. [sqlite3_bind_parameter_index][idx].
+    ///
+    /// [idx]: http://www.sqlite.org/c3ref/bind_parameter_index.html
+    pub fn bind_parameter_index(&self, name: &str) -> Option<ParamIx> {
+        let c_name = std::ffi::CString::new(name).ok()?;
+        let ix = unsafe { sqlite3_bind_parameter_index(self.raw(), c_name.as_ptr()) };
+        if ix == 0 { None } else { Some(ix as ParamIx) }
+    }
+}
+
+extern "C" {
+    fn sqlite3_bind_parameter_index(stmt: *mut core::RawStatement, name: *const libc::c_char) -> libc::c_int;
+}
+
+fn bind_named_values(s: &mut PreparedStatement, values: &[(&str, &dyn ToSql)]) -> SqliteResult<()> {
+    for &(name, v) in values.iter() {
+        let ix = s.bind_parameter_index(name).ok_or_else(|| SqliteError {
+            kind: SQLITE_MISUSE,
+            desc: "no such named parameter",
+            detail: Some(name.to_string()),
+        })?;
+        v.to_sql(s, ix)?;
+    }
+    Ok(())
+}
+
+/// Mix in a `query_named()` convenience function that binds parameters by
+/// name rather than by position.
+pub trait QueryNamed<F>
+    where F: FnMut(&mut ResultRow) -> SqliteResult<()>
+{
+    /// Process rows from a query after binding named parameters.
+    fn query_named(&mut self,
+                   values: &[(&str, &dyn ToSql)],
+                   each_row: &mut F
+                   ) -> SqliteResult<()>;
+}
+
+impl<F> QueryNamed<F> for core::PreparedStatement
+    where F: FnMut(&mut ResultRow) -> SqliteResult<()>
+{
+    fn query_named(&mut self,
+                   values: &[(&str, &dyn ToSql)],
+                   each_row: &mut F
+                   ) -> SqliteResult<()>
+    {
+        bind_named_values(self, values)?;
+        let mut results = self.execute();
+        loop {
+            match results.step()? {
+                None => break,
+                Some(ref mut row) => each_row(row)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Mix in an `update_named()` convenience function that binds parameters by
+/// name rather than by position.
+pub trait StatementUpdateNamed {
+    /// Execute a statement after binding named parameters.
+    fn update_named(&mut self, values: &[(&str, &dyn ToSql)]) -> SqliteResult<u64>;
+}
+
+impl StatementUpdateNamed for core::PreparedStatement {
+    fn update_named(&mut self, values: &[(&str, &dyn ToSql)]) -> SqliteResult<u64> {
+        let check = {
+            bind_named_values(self, values)?;
+            let mut results = self.execute();
+            match results.step()? {
+                None => Ok(()),
+                Some(_row) => Err(SqliteError {
+                    kind: SQLITE_MISUSE,
+                    desc: "unexpected SQLITE_ROW from update",
+                    detail: None
+                })
+            }
+        };
+        check.map(|_ok| self.changes())
+    }
+}
+
 
 /// Access result columns of a row by name or numeric index.
 pub trait ResultRowAccess {
@@ -347,8 +498,9 @@ enum_from_primitive! {
 #[cfg(test)]
 mod bind_tests {
     use super::{DatabaseConnection, ResultSet};
-    use super::{ResultRowAccess};
+    use super::{QueryMap, QueryNamed, ResultRowAccess, StatementUpdateNamed};
     use super::{SqliteResult};
+    use super::SqliteErrorCode::SQLITE_MISUSE;
 
     #[test]
     fn bind_fun() {
@@ -453,4 +605,49 @@ mod bind_tests {
         let expected = "SQLITE_ERROR: sqlite3_exec: near \"gobbledygook\": syntax error";
         assert_eq!(go(), expected.to_string())
     }
+
+    #[test]
+    fn named_params_bind_by_name() {
+        fn go() -> SqliteResult<String> {
+            let mut db = DatabaseConnection::in_memory()?;
+            db.exec("CREATE TABLE person (name)")?;
+            {
+                let mut tx = db.prepare("INSERT INTO person (name) VALUES (:name)")?;
+                tx.update_named(&[(":name", &"Dan".to_string())])?;
+            }
+
+            let mut name = String::new();
+            let mut stmt = db.prepare("SELECT name FROM person WHERE name = :name")?;
+            stmt.query_named(&[(":name", &"Dan".to_string())], &mut |row| {
+                name = row.get(0);
+                Ok(())
+            })?;
+            Ok(name)
+        }
+        assert_eq!(go(), Ok("Dan".to_string()));
+    }
+
+    #[test]
+    fn named_params_reject_unknown_name() {
+        fn go() -> SqliteResult<()> {
+            let mut db = DatabaseConnection::in_memory()?;
+            let mut tx = db.prepare("SELECT :known")?;
+            tx.update_named(&[(":not_a_param", &1i32)])
+        }
+        match go() {
+            Err(oops) => assert_eq!(oops.kind, SQLITE_MISUSE),
+            Ok(_) => panic!("expected SQLITE_MISUSE for an unknown parameter name"),
+        }
+    }
+
+    #[test]
+    fn query_map_collects_mapped_rows() {
+        fn go() -> SqliteResult<Vec<i32>> {
+            let mut db = DatabaseConnection::in_memory()?;
+            db.exec("CREATE TABLE t (n); INSERT INTO t VALUES (1), (2), (3)")?;
+            let mut stmt = db.prepare("SELECT n FROM t ORDER BY n")?;
+            stmt.query_map(&[], |row| Ok(row.get(0)))?.collect()
+        }
+        assert_eq!(go(), Ok(vec![1, 2, 3]));
+    }
 }
@@ -0,0 +1,134 @@
+//! Tracing and profiling hooks.
+//!
+//! cf. [sqlite3_trace][trace] and [sqlite3_profile][profile].
+//!
+//! [trace]: http://www.sqlite.org/c3ref/profile.html
+//! [profile]: http://www.sqlite.org/c3ref/profile.html
+//!
+//! *Known limitation*: a boxed callback is only freed when it is
+//! replaced by a later `trace`/`profile` call (sqlite hands back the
+//! previous call's argument, which is how these reclaim it). If a
+//! connection with a hook installed is simply dropped without first
+//! calling `trace(None)`/`profile(None)`, that box leaks for the rest
+//! of the process, since `DatabaseConnection`'s own teardown has no way
+//! to reach into this module to uninstall it first.
+//!
+//!   - *TODO: once `DatabaseConnection`'s `Drop` has a hook for
+//!     per-module teardown, call `trace(None)`/`profile(None)` from it
+//!     so this is no longer permanent debt.*
+
+use libc::{c_char, c_void};
+use std::ffi::CStr;
+use std::time::Duration;
+
+use super::core::DatabaseConnection;
+
+extern "C" {
+    fn sqlite3_trace(
+        db: *mut super::core::RawConnection,
+        xTrace: Option<unsafe extern "C" fn(*mut c_void, *const c_char)>,
+        pArg: *mut c_void,
+    ) -> *mut c_void;
+
+    fn sqlite3_profile(
+        db: *mut super::core::RawConnection,
+        xProfile: Option<unsafe extern "C" fn(*mut c_void, *const c_char, u64)>,
+        pArg: *mut c_void,
+    ) -> *mut c_void;
+}
+
+impl DatabaseConnection {
+    /// Install (or, with `None`, remove) a callback invoked with the
+    /// expanded SQL text of every statement as it runs.
+    ///
+    /// `sqlite3_trace` hands back whatever argument was passed to the
+    /// previous call, which is how the previously-installed closure
+    /// (if any) is recovered and freed here.
+    pub fn trace(&mut self, f: Option<Box<dyn FnMut(&str)>>) {
+        unsafe {
+            let old = match f {
+                Some(cb) => {
+                    let boxed = Box::into_raw(Box::new(cb));
+                    sqlite3_trace(self.raw(), Some(trace_trampoline), boxed as *mut c_void)
+                }
+                None => sqlite3_trace(self.raw(), None, std::ptr::null_mut()),
+            };
+            if !old.is_null() {
+                drop(Box::from_raw(old as *mut Box<dyn FnMut(&str)>));
+            }
+        }
+    }
+
+    /// Install (or, with `None`, remove) a callback invoked with the
+    /// SQL text and elapsed wall-clock time of every statement as it
+    /// finishes running.
+    pub fn profile(&mut self, f: Option<Box<dyn FnMut(&str, Duration)>>) {
+        unsafe {
+            let old = match f {
+                Some(cb) => {
+                    let boxed = Box::into_raw(Box::new(cb));
+                    sqlite3_profile(self.raw(), Some(profile_trampoline), boxed as *mut c_void)
+                }
+                None => sqlite3_profile(self.raw(), None, std::ptr::null_mut()),
+            };
+            if !old.is_null() {
+                drop(Box::from_raw(old as *mut Box<dyn FnMut(&str, Duration)>));
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn trace_trampoline(arg: *mut c_void, sql: *const c_char) {
+    let cb = &mut *(arg as *mut Box<dyn FnMut(&str)>);
+    if let Ok(sql) = CStr::from_ptr(sql).to_str() {
+        cb(sql);
+    }
+}
+
+unsafe extern "C" fn profile_trampoline(arg: *mut c_void, sql: *const c_char, nanos: u64) {
+    let cb = &mut *(arg as *mut Box<dyn FnMut(&str, Duration)>);
+    if let Ok(sql) = CStr::from_ptr(sql).to_str() {
+        cb(sql, Duration::from_nanos(nanos));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::super::{DatabaseConnection, SqliteResult};
+
+    #[test]
+    fn trace_fires_with_executed_sql() {
+        fn go() -> SqliteResult<Vec<String>> {
+            let seen = Rc::new(RefCell::new(vec!()));
+            let mut db = DatabaseConnection::in_memory()?;
+            {
+                let seen = seen.clone();
+                db.trace(Some(Box::new(move |sql| seen.borrow_mut().push(sql.to_string()))));
+            }
+            db.exec("CREATE TABLE t (n)")?;
+            db.trace(None);
+            Ok(Rc::try_unwrap(seen).unwrap().into_inner())
+        }
+        let seen = go().unwrap();
+        assert!(seen.iter().any(|sql| sql.contains("CREATE TABLE t")));
+    }
+
+    #[test]
+    fn profile_reports_an_elapsed_duration() {
+        fn go() -> SqliteResult<bool> {
+            let fired = Rc::new(RefCell::new(false));
+            let mut db = DatabaseConnection::in_memory()?;
+            {
+                let fired = fired.clone();
+                db.profile(Some(Box::new(move |_sql, _elapsed| *fired.borrow_mut() = true)));
+            }
+            db.exec("CREATE TABLE t (n)")?;
+            db.profile(None);
+            Ok(Rc::try_unwrap(fired).unwrap().into_inner())
+        }
+        assert!(go().unwrap());
+    }
+}
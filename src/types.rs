@@ -3,9 +3,10 @@
 use super::{PreparedStatement, ResultRow,
             ColIx, ParamIx};
 use super::{
-    SqliteResult,
+    SqliteError, SqliteResult,
 };
 use super::ColumnType::SQLITE_NULL;
+use super::SqliteErrorCode::SQLITE_MISMATCH;
 
 /// Values that can be bound to parameters in prepared statements.
 pub trait ToSql {
@@ -123,10 +124,62 @@ impl<'a> FromSql<'a> for &'a [u8] {
     }
 }
 
+/// Sqlite has no native 128-bit integer type, so `i128`/`u128` round
+/// trip through a fixed 16-byte blob, big-endian so that sqlite's
+/// `memcmp` blob ordering matches numeric ordering. `i128` additionally
+/// flips the sign bit, so that negative values sort before positive
+/// ones under that same byte-wise comparison.
+fn i128_to_be_bytes(v: i128) -> [u8; 16] {
+    ((v as u128) ^ (1u128 << 127)).to_be_bytes()
+}
+
+fn i128_from_be_bytes(bytes: [u8; 16]) -> i128 {
+    (u128::from_be_bytes(bytes) ^ (1u128 << 127)) as i128
+}
+
+fn blob16(row: &ResultRow, col: ColIx, what: &'static str) -> SqliteResult<[u8; 16]> {
+    let bytes = row.column_blob(col).unwrap_or(Vec::new());
+    if bytes.len() != 16 {
+        return Err(SqliteError {
+            kind: SQLITE_MISMATCH,
+            desc: what,
+            detail: Some(format!("expected a 16-byte blob, got {} bytes", bytes.len())),
+        });
+    }
+    let mut buf = [0u8; 16];
+    buf.copy_from_slice(&bytes);
+    Ok(buf)
+}
+
+impl ToSql for i128 {
+    fn to_sql(&self, s: &mut PreparedStatement, ix: ParamIx) -> SqliteResult<()> {
+        s.bind_blob(ix, &i128_to_be_bytes(*self))
+    }
+}
+
+impl<'a> FromSql<'a> for i128 {
+    fn from_sql(row: &'a ResultRow, col: ColIx) -> SqliteResult<i128> {
+        blob16(row, col, "i128 column").map(i128_from_be_bytes)
+    }
+}
+
+impl ToSql for u128 {
+    fn to_sql(&self, s: &mut PreparedStatement, ix: ParamIx) -> SqliteResult<()> {
+        s.bind_blob(ix, &self.to_be_bytes())
+    }
+}
+
+impl<'a> FromSql<'a> for u128 {
+    fn from_sql(row: &'a ResultRow, col: ColIx) -> SqliteResult<u128> {
+        blob16(row, col, "u128 column").map(u128::from_be_bytes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::{DatabaseConnection, SqliteResult, ResultSet};
-    use super::super::{ResultRowAccess};
+    use super::super::{Query, ResultRowAccess, StatementUpdate};
+    use super::super::SqliteErrorCode::SQLITE_MISMATCH;
 
     fn with_query<T, F>(sql: &str, mut f: F) -> SqliteResult<T>
         where F: FnMut(&mut ResultSet) -> T
@@ -152,6 +205,71 @@ mod tests {
             };
         }).unwrap();
     }
+
+    #[test]
+    fn i128_round_trips_via_to_sql_and_preserves_ordering() {
+        fn go() -> SqliteResult<Vec<i128>> {
+            let values: [i128; 4] = [i128::MIN, -1, 0, i128::MAX];
+
+            let mut db = DatabaseConnection::in_memory()?;
+            db.exec("CREATE TABLE t (v)")?;
+            {
+                let mut tx = db.prepare("INSERT INTO t (v) VALUES (?)")?;
+                for v in values.iter() {
+                    tx.update(&[v])?;
+                }
+            }
+
+            let mut got = vec!();
+            let mut stmt = db.prepare("SELECT v FROM t ORDER BY v")?;
+            stmt.query(&[], &mut |row| {
+                got.push(row.get(0));
+                Ok(())
+            })?;
+            Ok(got)
+        }
+        assert_eq!(go(), Ok(vec![i128::MIN, -1, 0, i128::MAX]));
+    }
+
+    #[test]
+    fn u128_round_trips_via_to_sql_and_preserves_ordering() {
+        fn go() -> SqliteResult<Vec<u128>> {
+            let values: [u128; 3] = [0, 1, u128::MAX];
+
+            let mut db = DatabaseConnection::in_memory()?;
+            db.exec("CREATE TABLE t (v)")?;
+            {
+                let mut tx = db.prepare("INSERT INTO t (v) VALUES (?)")?;
+                for v in values.iter() {
+                    tx.update(&[v])?;
+                }
+            }
+
+            let mut got = vec!();
+            let mut stmt = db.prepare("SELECT v FROM t ORDER BY v")?;
+            stmt.query(&[], &mut |row| {
+                got.push(row.get(0));
+                Ok(())
+            })?;
+            Ok(got)
+        }
+        assert_eq!(go(), Ok(vec![0, 1, u128::MAX]));
+    }
+
+    #[test]
+    fn i128_and_u128_reject_a_blob_of_the_wrong_size() {
+        fn go() -> (SqliteResult<i128>, SqliteResult<u128>) {
+            with_query("select x'0011'", |results| {
+                match results.step() {
+                    Ok(Some(ref mut row)) => (row.get_opt(0), row.get_opt(0)),
+                    other => panic!("unexpected step result: {:?}", other),
+                }
+            }).unwrap()
+        }
+        let (as_i128, as_u128) = go();
+        assert_eq!(as_i128.unwrap_err().kind, SQLITE_MISMATCH);
+        assert_eq!(as_u128.unwrap_err().kind, SQLITE_MISMATCH);
+    }
 }
 
 // Local Variables: